@@ -0,0 +1,86 @@
+use std::path::Path;
+use std::process::Command;
+use tracing;
+
+/// Snapshot of the git repository the run was started from
+#[derive(Clone, Debug, Default)]
+pub struct GitInfo {
+    pub root: String,
+    pub remote_url: String,
+    pub commit: String,
+    pub dirty: bool,
+}
+
+fn git(dir: &Path, args: &[&str]) -> Option<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+impl GitInfo {
+    /// Returns `None` if `dir` is not inside a git work tree or git is not installed
+    pub fn capture(dir: &Path) -> Option<GitInfo> {
+        let root = git(dir, &["rev-parse", "--show-toplevel"])?;
+        let commit = git(dir, &["rev-parse", "HEAD"]).unwrap_or_default();
+        let remote_url = git(dir, &["config", "--get", "remote.origin.url"]).unwrap_or_default();
+        let dirty = git(dir, &["status", "--porcelain", "--untracked-files=no"])
+            .is_some_and(|status| !status.is_empty());
+
+        tracing::debug!("Git repo at {} (commit {}, dirty: {})", root, commit, dirty);
+
+        Some(GitInfo {
+            root,
+            remote_url,
+            commit,
+            dirty,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    #[test]
+    fn captures_dirty_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        run_git(dir.path(), &["init", "-q"]);
+        std::fs::write(dir.path().join("train.py"), "print('a')\n").unwrap();
+        run_git(dir.path(), &["add", "train.py"]);
+        run_git(dir.path(), &["commit", "-q", "-m", "initial"]);
+
+        let clean = GitInfo::capture(dir.path()).unwrap();
+        assert!(!clean.dirty);
+        assert_eq!(clean.commit.len(), 40);
+        // wandb-core opens the repo at this path to write the patch
+        assert_eq!(
+            Path::new(&clean.root).canonicalize().unwrap(),
+            dir.path().canonicalize().unwrap()
+        );
+
+        std::fs::write(dir.path().join("train.py"), "print('b')\n").unwrap();
+        assert!(GitInfo::capture(dir.path()).unwrap().dirty);
+    }
+
+    #[test]
+    fn outside_repo_is_none() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(GitInfo::capture(dir.path()).is_none());
+    }
+}
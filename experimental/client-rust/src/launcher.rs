@@ -8,6 +8,7 @@ use tracing;
 
 pub struct Launcher {
     pub command: String,
+    pub ca_bundle: Option<String>,
 }
 
 fn wait_for_port(port_filename: &str) -> i32 {
@@ -34,6 +35,17 @@ fn wait_for_port(port_filename: &str) -> i32 {
 }
 
 impl Launcher {
+    fn core_command(&self, port_filename: &str) -> Command {
+        let mut command = Command::new(self.command.clone());
+        command.arg("--port-filename").arg(port_filename);
+        // wandb-core verifies server certificates against SSL_CERT_FILE when set,
+        // except on macOS, where Settings::validate rejects a ca_bundle
+        if let Some(ca_bundle) = &self.ca_bundle {
+            command.env("SSL_CERT_FILE", ca_bundle);
+        }
+        command
+    }
+
     pub fn start(&self) -> i32 {
         let port_file = NamedTempFile::new().expect("tempfile should be created");
         let port_filename = port_file.path().as_os_str().to_str().unwrap();
@@ -43,10 +55,7 @@ impl Launcher {
                 return port;
             }
             Ok(Fork::Child) => {
-                let _command = Command::new(self.command.clone())
-                    .arg("--port-filename")
-                    .arg(port_filename)
-                    .output();
+                let _output = self.core_command(port_filename).output();
             }
             Err(e) => {
                 sentry::capture_error(&std::io::Error::new(
@@ -59,3 +68,38 @@ impl Launcher {
         0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+
+    fn ssl_cert_file(launcher: &Launcher) -> Option<String> {
+        let command = launcher.core_command("port.txt");
+        command
+            .get_envs()
+            .find(|(key, _)| *key == OsStr::new("SSL_CERT_FILE"))
+            .and_then(|(_, value)| value.map(|value| value.to_string_lossy().to_string()))
+    }
+
+    #[test]
+    fn ca_bundle_is_passed_to_core() {
+        let launcher = Launcher {
+            command: "wandb-core".to_string(),
+            ca_bundle: Some("/etc/ssl/internal-ca.pem".to_string()),
+        };
+        assert_eq!(
+            ssl_cert_file(&launcher),
+            Some("/etc/ssl/internal-ca.pem".to_string())
+        );
+    }
+
+    #[test]
+    fn no_ca_bundle_leaves_environment_alone() {
+        let launcher = Launcher {
+            command: "wandb-core".to_string(),
+            ca_bundle: None,
+        };
+        assert_eq!(ssl_cert_file(&launcher), None);
+    }
+}
@@ -1,12 +1,15 @@
 use pyo3::prelude::*;
-use std::sync::OnceLock;
+use std::sync::{Mutex, OnceLock, PoisonError};
 
 use sentry;
 use tracing;
 use tracing_subscriber;
 
 pub mod connection;
+pub mod git;
 pub mod launcher;
+pub mod media;
+pub mod metric;
 pub mod printer;
 pub mod run;
 pub mod session;
@@ -26,11 +29,58 @@ pub fn get_core_version() -> &'static str {
     })
 }
 
+// the run returned by the last init call, handed back again unless reinit is set
+static CURRENT_RUN: Mutex<Option<Py<run::Run>>> = Mutex::new(None);
+
+#[derive(Debug, PartialEq)]
+enum InitAction {
+    Create,
+    Reuse,
+    FinishAndCreate,
+}
+
+fn init_action(current_run_finished: Option<bool>, reinit: bool) -> InitAction {
+    match (current_run_finished, reinit) {
+        (Some(false), false) => InitAction::Reuse,
+        (Some(false), true) => InitAction::FinishAndCreate,
+        _ => InitAction::Create,
+    }
+}
+
+fn current_run(py: Python<'_>) -> Option<Py<run::Run>> {
+    // a panic while holding the lock must not break every later init
+    let current_run = CURRENT_RUN.lock().unwrap_or_else(PoisonError::into_inner);
+    current_run.as_ref().map(|run| run.clone_ref(py))
+}
+
 #[pyfunction]
-pub fn init(settings: Option<settings::Settings>) -> run::Run {
+#[pyo3(signature = (settings=None, reinit=None))]
+pub fn init(
+    py: Python<'_>,
+    settings: Option<settings::Settings>,
+    reinit: Option<bool>,
+) -> PyResult<Py<run::Run>> {
     let actual_settings = settings.unwrap_or_default();
+    actual_settings.validate()?;
+    let reinit = reinit.unwrap_or(actual_settings.proto.reinit == Some(true));
+
+    // the lock is not held while talking to wandb-core, which can panic
+    let previous_run = current_run(py);
+    let previous_finished = previous_run.as_ref().map(|run| run.borrow(py).finished);
+    match (init_action(previous_finished, reinit), previous_run) {
+        (InitAction::Reuse, Some(run)) => {
+            tracing::warn!("A run is already in progress, returning it");
+            return Ok(run);
+        }
+        (InitAction::FinishAndCreate, Some(run)) => run.borrow_mut(py).finish(),
+        _ => {}
+    }
+
     let sess = session::Session::new(actual_settings);
-    sess.init_run(None)
+    let run = Py::new(py, sess.init_run(None, None))?;
+    *CURRENT_RUN.lock().unwrap_or_else(PoisonError::into_inner) = Some(run.clone_ref(py));
+
+    Ok(run)
 }
 
 /// A Python module implemented in Rust. The name of this function must match
@@ -52,5 +102,34 @@ fn wandb_core(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     m.add_class::<settings::Settings>()?;
     m.add_class::<session::Session>()?;
     m.add_class::<run::Run>()?;
+    m.add_class::<run::RunGroup>()?;
+    m.add_class::<media::Html>()?;
+    m.add_class::<media::Markdown>()?;
+    m.add_class::<media::Object3D>()?;
+    m.add_class::<media::Molecule>()?;
+    m.add_class::<media::File>()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn init_without_current_run_creates() {
+        assert_eq!(init_action(None, false), InitAction::Create);
+        assert_eq!(init_action(None, true), InitAction::Create);
+    }
+
+    #[test]
+    fn init_reuses_active_run_unless_reinit() {
+        assert_eq!(init_action(Some(false), false), InitAction::Reuse);
+        assert_eq!(init_action(Some(false), true), InitAction::FinishAndCreate);
+    }
+
+    #[test]
+    fn init_after_finish_creates() {
+        assert_eq!(init_action(Some(true), false), InitAction::Create);
+        assert_eq!(init_action(Some(true), true), InitAction::Create);
+    }
+}
@@ -0,0 +1,416 @@
+use numpy::PyReadonlyArrayDyn;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use serde_json::json;
+use sha2::Digest;
+use std::collections::HashMap;
+use std::path::Path;
+
+// rich text is uploaded as-is, keep it from blowing up the files dir
+const MAX_TEXT_MEDIA_BYTES: usize = 1 << 20;
+
+/// Media logged by writing it to the run's files dir; `save` returns the
+/// history value pointing at the written file
+pub trait Media {
+    fn save(&self, path: &str) -> HashMap<String, serde_json::Value>;
+}
+
+const HTML_EXTENSIONS: [&str; 2] = ["html", "htm"];
+const MARKDOWN_EXTENSIONS: [&str; 1] = ["md"];
+const OBJECT3D_EXTENSIONS: [&str; 5] = ["obj", "gltf", "glb", "babylon", "stl"];
+const MOLECULE_EXTENSIONS: [&str; 10] = [
+    "pdb", "pqr", "mmcif", "mcif", "cif", "sdf", "sd", "gro", "mol2", "mmtf",
+];
+
+fn read_media_file(path: &str, extensions: &[&str]) -> PyResult<(Vec<u8>, String)> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    if !extensions.contains(&extension.as_str()) {
+        return Err(PyValueError::new_err(format!(
+            "Unsupported file type '{}', expected one of: {}",
+            path,
+            extensions.join(", ")
+        )));
+    }
+    let content = std::fs::read(path)
+        .map_err(|e| PyValueError::new_err(format!("Failed to read {}: {}", path, e)))?;
+    Ok((content, extension))
+}
+
+fn read_text_media(data: String, extensions: &[&str]) -> PyResult<String> {
+    // treat the input as a path only if it points to an existing file of the right type
+    let path = Path::new(&data);
+    let is_media_file = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| extensions.contains(&ext.to_lowercase().as_str()));
+    let content = if is_media_file && path.is_file() {
+        std::fs::read_to_string(path).map_err(|e| {
+            PyValueError::new_err(format!("Failed to read {}: {}", path.display(), e))
+        })?
+    } else {
+        data
+    };
+
+    if content.len() > MAX_TEXT_MEDIA_BYTES {
+        return Err(PyValueError::new_err(format!(
+            "Media content is {} bytes, the limit is {} bytes",
+            content.len(),
+            MAX_TEXT_MEDIA_BYTES
+        )));
+    }
+
+    Ok(content)
+}
+
+fn save_media_file(
+    content: &[u8],
+    media_type: &str,
+    dir: &str,
+    extension: &str,
+    path: &str,
+) -> HashMap<String, serde_json::Value> {
+    let mut hasher = sha2::Sha256::new();
+    hasher.update(content);
+    let sha256_str = format!("{:x}", hasher.finalize());
+
+    std::fs::create_dir_all(format!("{}/media/{}", path, dir)).unwrap();
+    let media_path = format!("media/{}/{}.{}", dir, &sha256_str[..20], extension);
+    let full_path = format!("{}/{}", path, media_path);
+    std::fs::write(&full_path, content).unwrap();
+
+    let mut json = HashMap::new();
+    json.insert("_type".to_string(), json!(media_type));
+    json.insert("path".to_string(), json!(media_path));
+    json.insert("sha256".to_string(), json!(sha256_str));
+    json.insert("size".to_string(), json!(content.len()));
+
+    json
+}
+
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct Html {
+    pub content: String,
+}
+
+#[pymethods]
+impl Html {
+    #[new]
+    pub fn new(data: String) -> PyResult<Html> {
+        let content = read_text_media(data, &HTML_EXTENSIONS)?;
+        Ok(Html { content })
+    }
+}
+
+impl Media for Html {
+    fn save(&self, path: &str) -> HashMap<String, serde_json::Value> {
+        save_media_file(self.content.as_bytes(), "html-file", "html", "html", path)
+    }
+}
+
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct Markdown {
+    pub content: String,
+}
+
+#[pymethods]
+impl Markdown {
+    #[new]
+    pub fn new(data: String) -> PyResult<Markdown> {
+        let content = read_text_media(data, &MARKDOWN_EXTENSIONS)?;
+        Ok(Markdown { content })
+    }
+}
+
+impl Media for Markdown {
+    fn save(&self, path: &str) -> HashMap<String, serde_json::Value> {
+        save_media_file(
+            self.content.as_bytes(),
+            "markdown-file",
+            "markdown",
+            "md",
+            path,
+        )
+    }
+}
+
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct Object3D {
+    // rows of xyz or xyzrgb
+    pub points: Vec<Vec<f64>>,
+    // contents and extension of a mesh file, used instead of points
+    pub file: Option<(Vec<u8>, String)>,
+}
+
+#[pymethods]
+impl Object3D {
+    #[new]
+    pub fn new(data: &PyAny) -> PyResult<Object3D> {
+        if let Ok(path) = data.extract::<String>() {
+            let file = read_media_file(&path, &OBJECT3D_EXTENSIONS)?;
+            return Ok(Object3D {
+                points: vec![],
+                file: Some(file),
+            });
+        }
+
+        // numpy defaults to int64 for integer data, accept the common dtypes
+        if let Ok(arr) = data.extract::<PyReadonlyArrayDyn<'_, f64>>() {
+            return Object3D::from_points(array_rows(arr, |v| v));
+        }
+        if let Ok(arr) = data.extract::<PyReadonlyArrayDyn<'_, f32>>() {
+            return Object3D::from_points(array_rows(arr, f64::from));
+        }
+        if let Ok(arr) = data.extract::<PyReadonlyArrayDyn<'_, i64>>() {
+            return Object3D::from_points(array_rows(arr, |v| v as f64));
+        }
+        if let Ok(arr) = data.extract::<PyReadonlyArrayDyn<'_, i32>>() {
+            return Object3D::from_points(array_rows(arr, f64::from));
+        }
+        Err(PyValueError::new_err(
+            "Object3D expects a path or a numpy array of float64, float32, int64 or int32",
+        ))
+    }
+}
+
+// shape and rows of any array, regardless of memory layout
+fn array_rows<T: numpy::Element + Copy>(
+    arr: PyReadonlyArrayDyn<'_, T>,
+    to_f64: fn(T) -> f64,
+) -> (Vec<usize>, Vec<Vec<f64>>) {
+    let view = arr.as_array();
+    let shape = view.shape().to_vec();
+    if shape.len() != 2 {
+        return (shape, vec![]);
+    }
+    let rows = view
+        .rows()
+        .into_iter()
+        .map(|row| row.iter().map(|&v| to_f64(v)).collect())
+        .collect();
+    (shape, rows)
+}
+
+impl Object3D {
+    fn from_points((shape, points): (Vec<usize>, Vec<Vec<f64>>)) -> PyResult<Object3D> {
+        if shape.len() != 2 || (shape[1] != 3 && shape[1] != 6) {
+            return Err(PyValueError::new_err(format!(
+                "Point cloud must have shape Nx3 or Nx6, got {:?}",
+                shape
+            )));
+        }
+
+        let colors_in_range = points
+            .iter()
+            .all(|row| row[3..].iter().all(|&c| (0.0..=255.0).contains(&c)));
+        if !colors_in_range {
+            return Err(PyValueError::new_err(
+                "Point cloud colors must be in the range [0, 255]",
+            ));
+        }
+
+        Ok(Object3D { points, file: None })
+    }
+}
+
+impl Media for Object3D {
+    fn save(&self, path: &str) -> HashMap<String, serde_json::Value> {
+        match &self.file {
+            Some((content, extension)) => {
+                save_media_file(content, "object3D-file", "object3D", extension, path)
+            }
+            None => {
+                let content = serde_json::to_string(&self.points).unwrap();
+                let mut json = save_media_file(
+                    content.as_bytes(),
+                    "object3D-file",
+                    "object3D",
+                    "pts.json",
+                    path,
+                );
+                json.insert("num_points".to_string(), json!(self.points.len()));
+                json
+            }
+        }
+    }
+}
+
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct Molecule {
+    pub content: Vec<u8>,
+    pub extension: String,
+}
+
+#[pymethods]
+impl Molecule {
+    #[new]
+    pub fn new(path: String) -> PyResult<Molecule> {
+        let (content, extension) = read_media_file(&path, &MOLECULE_EXTENSIONS)?;
+        Ok(Molecule { content, extension })
+    }
+}
+
+impl Media for Molecule {
+    fn save(&self, path: &str) -> HashMap<String, serde_json::Value> {
+        save_media_file(
+            &self.content,
+            "molecule-file",
+            "molecule",
+            &self.extension,
+            path,
+        )
+    }
+}
+
+/// Already-encoded bytes (a PDF, a serialized model, ...) attached as-is
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct File {
+    pub content: Vec<u8>,
+    pub name: String,
+    pub mime_type: Option<String>,
+}
+
+#[pymethods]
+impl File {
+    #[new]
+    pub fn new(data: &PyBytes, name: String, mime_type: Option<String>) -> File {
+        File {
+            content: data.as_bytes().to_vec(),
+            name,
+            mime_type,
+        }
+    }
+}
+
+impl File {
+    // html needs nothing beyond path and digest, so it can reuse the type the
+    // frontend renders; image and video types also expect dimensions
+    fn media_type(&self) -> &'static str {
+        match self.mime_type.as_deref() {
+            Some("text/html") => "html-file",
+            _ => "file",
+        }
+    }
+}
+
+impl Media for File {
+    fn save(&self, path: &str) -> HashMap<String, serde_json::Value> {
+        let extension = Path::new(&self.name)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("bin");
+        let mut json = save_media_file(&self.content, self.media_type(), "files", extension, path);
+        json.insert("name".to_string(), json!(self.name));
+        if let Some(mime_type) = &self.mime_type {
+            json.insert("mime_type".to_string(), json!(mime_type));
+        }
+        json
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn point_cloud_is_saved_with_count_and_layout() {
+        let dir = tempfile::tempdir().unwrap();
+        let points = vec![
+            vec![0.0, 1.0, 2.0, 255.0, 0.0, 0.0],
+            vec![3.0, 4.0, 5.0, 0.0, 255.0, 0.0],
+        ];
+        let object3d = Object3D::from_points((vec![2, 6], points)).unwrap();
+        let json = object3d.save(dir.path().to_str().unwrap());
+
+        assert_eq!(json["_type"], "object3D-file");
+        assert_eq!(json["num_points"], 2);
+        let path = json["path"].as_str().unwrap();
+        assert!(path.ends_with(".pts.json"));
+        let saved = std::fs::read_to_string(dir.path().join(path)).unwrap();
+        assert_eq!(
+            saved,
+            "[[0.0,1.0,2.0,255.0,0.0,0.0],[3.0,4.0,5.0,0.0,255.0,0.0]]"
+        );
+    }
+
+    #[test]
+    fn point_cloud_shape_and_colors_are_validated() {
+        let xyz = vec![vec![0.0, 1.0, 2.0]];
+        assert!(Object3D::from_points((vec![1, 3], xyz)).is_ok());
+
+        let xy = vec![vec![0.0, 1.0]];
+        assert!(Object3D::from_points((vec![1, 2], xy)).is_err());
+
+        let bad_color = vec![vec![0.0, 1.0, 2.0, 300.0, 0.0, 0.0]];
+        assert!(Object3D::from_points((vec![1, 6], bad_color)).is_err());
+    }
+
+    #[test]
+    fn raw_bytes_are_stored_with_matching_digest() {
+        let dir = tempfile::tempdir().unwrap();
+        let content = b"%PDF-1.4 not really a pdf \x00\xff".to_vec();
+        let file = File {
+            content: content.clone(),
+            name: "report.pdf".to_string(),
+            mime_type: Some("application/pdf".to_string()),
+        };
+        let json = file.save(dir.path().to_str().unwrap());
+
+        let expected = format!("{:x}", sha2::Sha256::digest(&content));
+        assert_eq!(json["sha256"], expected.as_str());
+        assert_eq!(json["_type"], "file");
+        let path = json["path"].as_str().unwrap();
+        assert!(path.ends_with(".pdf"));
+        let saved = std::fs::read(dir.path().join(path)).unwrap();
+        assert_eq!(format!("{:x}", sha2::Sha256::digest(&saved)), expected);
+    }
+
+    #[test]
+    fn raw_bytes_use_known_media_type_when_possible() {
+        let file = |mime_type: &str| File {
+            content: vec![],
+            name: "x".to_string(),
+            mime_type: Some(mime_type.to_string()),
+        };
+        assert_eq!(file("text/html").media_type(), "html-file");
+        assert_eq!(file("image/png").media_type(), "file");
+        assert_eq!(file("application/octet-stream").media_type(), "file");
+    }
+
+    #[test]
+    fn inline_html_is_saved_as_html_media() {
+        let dir = tempfile::tempdir().unwrap();
+        let files_dir = dir.path().to_str().unwrap();
+
+        let html = Html::new("<h1>hello</h1>".to_string()).unwrap();
+        let json = html.save(files_dir);
+
+        assert_eq!(json["_type"], "html-file");
+        assert_eq!(json["size"], 14);
+        let path = json["path"].as_str().unwrap();
+        assert!(path.starts_with("media/html/") && path.ends_with(".html"));
+        let saved = std::fs::read_to_string(dir.path().join(path)).unwrap();
+        assert_eq!(saved, "<h1>hello</h1>");
+    }
+
+    #[test]
+    fn html_is_read_from_html_and_htm_files() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["page.html", "page.htm", "PAGE.HTM"] {
+            let path = dir.path().join(name);
+            std::fs::write(&path, "<p>from file</p>").unwrap();
+            let html = Html::new(path.to_str().unwrap().to_string()).unwrap();
+            assert_eq!(html.content, "<p>from file</p>");
+        }
+    }
+}
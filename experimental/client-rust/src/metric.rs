@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::wandb_internal;
+
+/// How a metric's history is reduced into its summary value
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SummaryAggregation {
+    Min,
+    Max,
+    Mean,
+    Last,
+    None,
+}
+
+impl FromStr for SummaryAggregation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "min" => Ok(SummaryAggregation::Min),
+            "max" => Ok(SummaryAggregation::Max),
+            "mean" => Ok(SummaryAggregation::Mean),
+            "last" => Ok(SummaryAggregation::Last),
+            "none" => Ok(SummaryAggregation::None),
+            _ => Err(format!(
+                "Invalid summary aggregation '{}', expected one of: min, max, mean, last, none",
+                s
+            )),
+        }
+    }
+}
+
+impl SummaryAggregation {
+    pub fn to_proto(&self) -> wandb_internal::MetricSummary {
+        let mut summary = wandb_internal::MetricSummary::default();
+        match self {
+            SummaryAggregation::Min => summary.min = true,
+            SummaryAggregation::Max => summary.max = true,
+            SummaryAggregation::Mean => summary.mean = true,
+            SummaryAggregation::Last => summary.last = true,
+            SummaryAggregation::None => summary.none = true,
+        }
+        summary
+    }
+}
+
+/// A summary entry, shaped like wandb-core stores it
+#[derive(Clone, Debug, PartialEq)]
+pub enum SummaryValue {
+    // no aggregation defined, the last logged value
+    Flat(f64),
+    // defined aggregation, e.g. `{"min": 0.1}`
+    Aggregated(&'static str, f64),
+}
+
+// running statistics of every value logged for a key, like wandb-core's `Stats`
+#[derive(Clone, Copy, Debug)]
+struct Stats {
+    min: f64,
+    max: f64,
+    sum: f64,
+    count: u64,
+    latest: f64,
+}
+
+impl Stats {
+    fn new(value: f64) -> Stats {
+        Stats {
+            min: value,
+            max: value,
+            sum: value,
+            count: 1,
+            latest: value,
+        }
+    }
+
+    fn update(&mut self, value: f64) {
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value;
+        self.count += 1;
+        self.latest = value;
+    }
+}
+
+/// Local copy of the run summary. Like wandb-core, statistics cover every
+/// value since the run started, even those logged before `define`.
+#[derive(Debug, Default)]
+pub struct Summary {
+    aggregations: HashMap<String, SummaryAggregation>,
+    stats: HashMap<String, Stats>,
+}
+
+impl Summary {
+    pub fn define(&mut self, key: &str, aggregation: SummaryAggregation) {
+        self.aggregations.insert(key.to_string(), aggregation);
+    }
+
+    pub fn update(&mut self, key: &str, value: f64) {
+        self.stats
+            .entry(key.to_string())
+            .and_modify(|stats| stats.update(value))
+            .or_insert_with(|| Stats::new(value));
+    }
+
+    pub fn get(&self, key: &str) -> Option<SummaryValue> {
+        let stats = self.stats.get(key)?;
+        let (name, value) = match self.aggregations.get(key) {
+            None => return Some(SummaryValue::Flat(stats.latest)),
+            Some(SummaryAggregation::Min) => ("min", stats.min),
+            Some(SummaryAggregation::Max) => ("max", stats.max),
+            Some(SummaryAggregation::Mean) => ("mean", stats.sum / stats.count as f64),
+            Some(SummaryAggregation::Last) => ("last", stats.latest),
+            Some(SummaryAggregation::None) => return None,
+        };
+        Some(SummaryValue::Aggregated(name, value))
+    }
+
+    pub fn values(&self) -> HashMap<String, SummaryValue> {
+        self.stats
+            .keys()
+            .filter_map(|key| self.get(key).map(|value| (key.clone(), value)))
+            .collect()
+    }
+}
+
+/// Builds the record for `define_metric`. With a step metric, wandb-core
+/// imputes its latest value into every row that logs the metric (step_sync).
+pub fn metric_record(
+    name: &str,
+    step_metric: Option<String>,
+    aggregation: Option<SummaryAggregation>,
+) -> wandb_internal::MetricRecord {
+    wandb_internal::MetricRecord {
+        name: name.to_string(),
+        options: Some(wandb_internal::MetricOptions {
+            defined: true,
+            step_sync: step_metric.is_some(),
+            ..Default::default()
+        }),
+        step_metric: step_metric.unwrap_or_default(),
+        summary: aggregation.map(|aggregation| aggregation.to_proto()),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_min_keeps_minimum() {
+        let mut summary = Summary::default();
+        summary.define("loss", SummaryAggregation::Min);
+        for value in [0.9, 0.5, 0.7, 0.2, 0.4] {
+            summary.update("loss", value);
+        }
+        assert_eq!(
+            summary.get("loss"),
+            Some(SummaryValue::Aggregated("min", 0.2))
+        );
+    }
+
+    #[test]
+    fn summary_without_definition_is_last_value() {
+        let mut summary = Summary::default();
+        summary.update("acc", 0.1);
+        summary.update("acc", 0.3);
+        assert_eq!(summary.get("acc"), Some(SummaryValue::Flat(0.3)));
+    }
+
+    #[test]
+    fn summary_defined_after_logging_covers_earlier_values() {
+        let mut summary = Summary::default();
+        summary.update("loss", 1.0);
+        summary.update("loss", 2.0);
+        summary.define("loss", SummaryAggregation::Mean);
+        summary.update("loss", 4.0);
+        let Some(SummaryValue::Aggregated("mean", mean)) = summary.get("loss") else {
+            panic!("expected a mean");
+        };
+        assert!((mean - 7.0 / 3.0).abs() < 1e-12);
+
+        summary.define("loss", SummaryAggregation::Min);
+        assert_eq!(
+            summary.get("loss"),
+            Some(SummaryValue::Aggregated("min", 1.0))
+        );
+    }
+
+    #[test]
+    fn metric_with_step_metric_is_step_synced() {
+        let record = metric_record("val/loss", Some("epoch".to_string()), None);
+        assert_eq!(record.name, "val/loss");
+        assert_eq!(record.step_metric, "epoch");
+        let options = record.options.unwrap();
+        assert!(options.defined);
+        assert!(options.step_sync);
+        assert!(record.summary.is_none());
+    }
+
+    #[test]
+    fn metric_without_step_metric_is_not_step_synced() {
+        let record = metric_record("loss", None, Some(SummaryAggregation::Min));
+        assert_eq!(record.step_metric, "");
+        assert!(!record.options.unwrap().step_sync);
+        assert!(record.summary.unwrap().min);
+    }
+
+    #[test]
+    fn summary_none_is_dropped() {
+        let mut summary = Summary::default();
+        summary.define("loss", SummaryAggregation::None);
+        summary.update("loss", 1.0);
+        assert_eq!(summary.get("loss"), None);
+    }
+}
@@ -1,15 +1,21 @@
 use pyo3::prelude::*;
 
 use crate::connection::Interface;
+use crate::git::GitInfo;
+use crate::media::{File, Html, Markdown, Media, Molecule, Object3D};
+use crate::metric::{metric_record, Summary, SummaryAggregation, SummaryValue};
 use crate::wandb_internal;
 use chrono;
 use image;
 use numpy::PyReadonlyArrayDyn;
+use pyo3::exceptions::PyValueError;
+use pyo3::types::PyDict;
 use rand::seq::SliceRandom;
 use rand::thread_rng;
 use serde::{Serialize, Serializer};
 use sha2::Digest;
 use std::collections::HashMap;
+use std::path::Path;
 use tracing;
 
 use crate::printer;
@@ -50,6 +56,11 @@ pub enum Value<'py> {
     Int(i32),
     Str(String),
     Ndarray(PyReadonlyArrayDyn<'py, f64>),
+    Html(Html),
+    Markdown(Markdown),
+    Object3D(Object3D),
+    Molecule(Molecule),
+    File(File),
 }
 
 impl<'py> Serialize for Value<'py> {
@@ -66,6 +77,24 @@ impl<'py> Serialize for Value<'py> {
                 let vec_data: Vec<f64> = arr.as_slice().unwrap().to_vec();
                 vec_data.serialize(serializer)
             }
+            Value::Html(_)
+            | Value::Markdown(_)
+            | Value::Object3D(_)
+            | Value::Molecule(_)
+            | Value::File(_) => unreachable!("media is saved to a file, not serialized"),
+        }
+    }
+}
+
+impl<'py> Value<'py> {
+    fn as_media(&self) -> Option<&dyn Media> {
+        match self {
+            Value::Html(html) => Some(html),
+            Value::Markdown(markdown) => Some(markdown),
+            Value::Object3D(object3d) => Some(object3d),
+            Value::Molecule(molecule) => Some(molecule),
+            Value::File(file) => Some(file),
+            _ => None,
         }
     }
 }
@@ -102,10 +131,81 @@ fn ndarray_to_image(arr: PyReadonlyArrayDyn<'_, f64>, path: &String) -> HashMap<
     json
 }
 
+/// Step bookkeeping for history rows
+#[derive(Debug, Default)]
+pub struct Steps {
+    // step of the row currently being written
+    pub current: i64,
+    // highest step whose row has been flushed
+    pub committed: Option<i64>,
+    // whether the current row has uncommitted data
+    pub pending: bool,
+}
+
+impl Steps {
+    /// Step and flush flag for the next row, `None` if an explicit step goes backwards
+    fn begin_row(&mut self, step: Option<i64>, commit: Option<bool>) -> Option<(i64, bool)> {
+        if let Some(step) = step {
+            // a row in progress can only be extended or moved past, a committed one is final
+            let backwards = if self.pending {
+                step < self.current
+            } else {
+                self.committed.is_some_and(|committed| step <= committed)
+            };
+            if backwards {
+                return None;
+            }
+            if step != self.current {
+                // moving on to a new step commits the row in progress
+                if self.pending {
+                    self.committed = Some(self.current);
+                    self.pending = false;
+                }
+                self.current = step;
+            }
+        }
+        // with an explicit step, the row is committed once the step moves on
+        Some((self.current, commit.unwrap_or(step.is_none())))
+    }
+
+    fn end_row(&mut self, commit: bool, stride: i64) {
+        if commit {
+            self.committed = Some(self.current);
+            self.current += stride;
+            self.pending = false;
+        } else {
+            self.pending = true;
+        }
+    }
+}
+
 #[pyclass]
 pub struct Run {
     pub settings: Settings,
     pub interface: Interface,
+    pub summary: Summary,
+    pub steps: Steps,
+    pub git: Option<GitInfo>,
+    #[pyo3(get)]
+    pub finished: bool,
+}
+
+/// The script Python is running, as `__main__` knows it; `None` in a REPL
+fn main_program() -> Option<String> {
+    Python::with_gil(|py| {
+        py.import("__main__")
+            .and_then(|main| main.getattr("__file__"))
+            .and_then(|file| file.extract())
+            .ok()
+    })
+}
+
+/// Absolute path of the program and its path relative to `root`,
+/// `None` if it doesn't exist or lives outside `root`
+fn program_paths(program: &str, cwd: &Path, root: &Path) -> Option<(String, String)> {
+    let abspath = cwd.join(program).canonicalize().ok()?;
+    let relpath = abspath.strip_prefix(root.canonicalize().ok()?).ok()?;
+    Some((abspath.display().to_string(), relpath.display().to_string()))
 }
 
 impl Run {
@@ -158,6 +258,36 @@ impl Run {
         self.settings.proto.sync_file = Some(format!("{}/run-{}.wandb", sync_dir, run_id));
         self.settings.proto.files_dir = Some(format!("{}/files", sync_dir));
 
+        // with save_code, wandb-core writes diff.patch from root_dir and copies
+        // the program into the run's files, so it only needs to find both
+        let cwd = std::env::current_dir().ok();
+        if self.settings.proto.disable_git != Some(true) {
+            // the working directory may have been deleted, there's no repo to capture then
+            self.git = cwd.as_deref().and_then(GitInfo::capture);
+        }
+        let root_dir = match (&self.git, &cwd) {
+            (Some(git), _) => Some(git.root.clone()),
+            (None, Some(cwd)) => Some(cwd.display().to_string()),
+            (None, None) => None,
+        };
+        if self.settings.proto.program.is_none() {
+            self.settings.proto.program = main_program();
+        }
+        if let (Some(program), Some(cwd), Some(root_dir)) =
+            (&self.settings.proto.program, &cwd, &root_dir)
+        {
+            if let Some((abspath, relpath)) = program_paths(program, cwd, Path::new(root_dir)) {
+                self.settings.proto.program_abspath = Some(abspath);
+                self.settings.proto.program_relpath = Some(relpath);
+            }
+        }
+        self.settings.proto.root_dir = root_dir;
+        if let Some(git) = &self.git {
+            self.settings.proto.git_root = Some(git.root.clone());
+            self.settings.proto.git_commit = Some(git.commit.clone());
+            self.settings.proto.git_remote_url = Some(git.remote_url.clone());
+        }
+
         let server_inform_init_request = wandb_internal::ServerRequest {
             server_request_type: Some(
                 wandb_internal::server_request::ServerRequestType::InformInit(
@@ -183,6 +313,10 @@ impl Run {
                     run_id: self.id(),
                     project: self.project(),
                     // display_name: "gooba-gaba".to_string(),
+                    git: self.git.as_ref().map(|git| wandb_internal::GitRepoRecord {
+                        remote_url: git.remote_url.clone(),
+                        commit: git.commit.clone(),
+                    }),
                     info: Some(wandb_internal::RecordInfo {
                         stream_id: self.id(),
                         ..Default::default()
@@ -277,10 +411,87 @@ impl Run {
     //     self.log(serde_json::from_str(&data).unwrap_or(HashMap::new()));
     // }
 
-    pub fn log(&self, data: HashMap<String, Value>) {
+    pub fn define_metric(
+        &mut self,
+        name: String,
+        step_metric: Option<String>,
+        summary: Option<String>,
+    ) -> PyResult<()> {
+        tracing::debug!("Defining metric {} for run {}", name, self.id());
+
+        let aggregation = match summary {
+            Some(summary) => Some(
+                summary
+                    .parse::<SummaryAggregation>()
+                    .map_err(PyValueError::new_err)?,
+            ),
+            None => None,
+        };
+
+        let metric_record = metric_record(&name, step_metric, aggregation);
+
+        if let Some(aggregation) = aggregation {
+            self.summary.define(&name, aggregation);
+        }
+
+        let record = wandb_internal::Record {
+            record_type: Some(wandb_internal::record::RecordType::Metric(metric_record)),
+            info: Some(wandb_internal::RecordInfo {
+                stream_id: self.id(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let message = wandb_internal::ServerRequest {
+            server_request_type: Some(
+                wandb_internal::server_request::ServerRequestType::RecordPublish(record),
+            ),
+        };
+
+        self.interface.conn.send_message(&message).unwrap();
+
+        Ok(())
+    }
+
+    #[getter]
+    pub fn git_dirty(&self) -> bool {
+        self.git.as_ref().is_some_and(|git| git.dirty)
+    }
+
+    #[getter]
+    pub fn summary(&self, py: Python<'_>) -> PyResult<PyObject> {
+        let summary = PyDict::new(py);
+        for (key, value) in self.summary.values() {
+            match value {
+                SummaryValue::Flat(value) => summary.set_item(key, value)?,
+                SummaryValue::Aggregated(name, value) => {
+                    let aggregated = PyDict::new(py);
+                    aggregated.set_item(name, value)?;
+                    summary.set_item(key, aggregated)?;
+                }
+            }
+        }
+        Ok(summary.into())
+    }
+
+    /// Highest step committed so far, `None` until the first row is committed
+    pub fn step(&self) -> Option<u64> {
+        self.steps.committed.map(|step| step as u64)
+    }
+
+    #[pyo3(signature = (data, step=None, commit=None))]
+    pub fn log(&mut self, data: HashMap<String, Value>, step: Option<i64>, commit: Option<bool>) {
         tracing::debug!("Logging to run {}", self.id());
 
-        // TODO: make it work with steps
+        let Some((row_step, commit)) = self.steps.begin_row(step, commit) else {
+            tracing::warn!(
+                "Step {} is before an already logged step, dropping data",
+                step.unwrap_or_default()
+            );
+            return;
+        };
+
         // let history_record = wandb_internal::HistoryRecord {
         //     item: data
         //         .iter()
@@ -311,6 +522,8 @@ impl Run {
         // self.interface.conn.send_message(&message).unwrap();
 
         let mut partial_history_request = wandb_internal::PartialHistoryRequest {
+            step: Some(wandb_internal::HistoryStep { num: row_step }),
+            action: Some(wandb_internal::HistoryAction { flush: commit }),
             ..Default::default()
         };
 
@@ -319,6 +532,13 @@ impl Run {
                 key: k.clone(),
                 ..Default::default()
             };
+            if let Some(media) = v.as_media() {
+                let value_json = media.save(&self.settings.files_dir());
+                item.value_json = serde_json::to_string(&value_json).unwrap();
+                self.save_files(&value_json["path"].as_str().unwrap().to_string());
+                partial_history_request.item.push(item);
+                continue;
+            }
             match v {
                 Value::Ndarray(arr) => {
                     // TODO: convert to image if shape is valid, otherwise just serialize
@@ -332,6 +552,14 @@ impl Run {
                         item.value_json = serde_json::to_string(&Value::Ndarray(arr)).unwrap();
                     }
                 }
+                Value::Float(f) => {
+                    self.summary.update(&k, f);
+                    item.value_json = serde_json::to_string(&v).unwrap();
+                }
+                Value::Int(i) => {
+                    self.summary.update(&k, i as f64);
+                    item.value_json = serde_json::to_string(&v).unwrap();
+                }
                 _ => {
                     item.value_json = serde_json::to_string(&v).unwrap();
                 }
@@ -373,9 +601,16 @@ impl Run {
         };
 
         self.interface.conn.send_message(&message).unwrap();
+
+        self.steps.end_row(commit, self.settings.step_stride);
     }
 
     pub fn finish(&mut self) {
+        if self.finished {
+            tracing::debug!("Run {} is already finished", self.id());
+            return;
+        }
+        self.finished = true;
         tracing::debug!("Finishing run {}", self.id());
 
         let mut record = wandb_internal::Record {
@@ -595,3 +830,266 @@ impl Run {
         self.interface.conn.send_message(&message).unwrap();
     }
 }
+
+/// Runs in several projects fed by the same `log` calls. The group owns the
+/// step counter, so a row lands under the same step in every destination.
+#[pyclass]
+pub struct RunGroup {
+    runs: Vec<Run>,
+    steps: Steps,
+    step_stride: i64,
+}
+
+impl RunGroup {
+    pub fn new(runs: Vec<Run>) -> RunGroup {
+        let step_stride = runs.first().map_or(1, |run| run.settings.step_stride);
+        RunGroup {
+            runs,
+            steps: Steps::default(),
+            step_stride,
+        }
+    }
+}
+
+#[pymethods]
+impl RunGroup {
+    #[getter]
+    pub fn projects(&self) -> Vec<String> {
+        self.runs.iter().map(|run| run.project()).collect()
+    }
+
+    /// Logs to every run in the group, or only to the runs of `projects`
+    #[pyo3(signature = (data, step=None, commit=None, projects=None))]
+    pub fn log(
+        &mut self,
+        data: HashMap<String, Value>,
+        step: Option<i64>,
+        commit: Option<bool>,
+        projects: Option<Vec<String>>,
+    ) -> PyResult<()> {
+        if let Some(projects) = &projects {
+            let known = self.projects();
+            if let Some(unknown) = projects.iter().find(|project| !known.contains(project)) {
+                return Err(PyValueError::new_err(format!(
+                    "Project '{}' is not part of this run group",
+                    unknown
+                )));
+            }
+        }
+
+        let Some((row_step, commit)) = self.steps.begin_row(step, commit) else {
+            tracing::warn!(
+                "Step {} is before an already logged step, dropping data",
+                step.unwrap_or_default()
+            );
+            return Ok(());
+        };
+
+        // runs left out of a row skip its step, they never fall behind the group
+        for run in &mut self.runs {
+            let selected = match &projects {
+                Some(projects) => projects.contains(&run.project()),
+                None => true,
+            };
+            if selected {
+                run.log(data.clone(), Some(row_step), Some(commit));
+            }
+        }
+
+        self.steps.end_row(commit, self.step_stride);
+        Ok(())
+    }
+
+    pub fn finish(&mut self) {
+        for run in &mut self.runs {
+            run.finish();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Connection;
+    use prost::Message;
+    use std::io::Read;
+    use std::net::{TcpListener, TcpStream};
+
+    // what Run::log does with the step bookkeeping, minus the I/O
+    fn log(steps: &mut Steps, step: Option<i64>, commit: Option<bool>, stride: i64) -> i64 {
+        let (row_step, commit) = steps.begin_row(step, commit).unwrap();
+        steps.end_row(commit, stride);
+        row_step
+    }
+
+    #[test]
+    fn step_tracks_auto_increment_and_partial_rows() {
+        let mut steps = Steps::default();
+        assert_eq!(steps.committed, None);
+
+        for expected in 0..3 {
+            assert_eq!(log(&mut steps, None, None, 1), expected);
+        }
+        assert_eq!(steps.committed, Some(2));
+
+        // a partial row lands on the next step but doesn't commit it
+        assert_eq!(log(&mut steps, None, Some(false), 1), 3);
+        assert_eq!(steps.committed, Some(2));
+
+        assert_eq!(log(&mut steps, None, None, 1), 3);
+        assert_eq!(steps.committed, Some(3));
+    }
+
+    #[test]
+    fn explicit_step_commits_when_moving_on() {
+        let mut steps = Steps::default();
+        assert_eq!(log(&mut steps, Some(5), None, 1), 5);
+        assert_eq!(steps.committed, None);
+        assert_eq!(log(&mut steps, Some(5), None, 1), 5);
+        assert_eq!(log(&mut steps, Some(7), None, 1), 7);
+        assert_eq!(steps.committed, Some(5));
+    }
+
+    #[test]
+    fn explicit_step_cannot_go_backwards() {
+        let mut steps = Steps::default();
+        log(&mut steps, Some(5), Some(true), 1);
+        assert_eq!(steps.begin_row(Some(3), None), None);
+        assert_eq!(steps.begin_row(Some(5), None), None);
+        assert_eq!(steps.current, 6);
+
+        // a pending row can't be moved back either
+        log(&mut steps, Some(8), Some(false), 1);
+        assert_eq!(steps.begin_row(Some(7), None), None);
+        assert_eq!(steps.current, 8);
+    }
+
+    #[test]
+    fn program_paths_are_relative_to_root() {
+        let root = tempfile::tempdir().unwrap();
+        let cwd = root.path().join("scripts");
+        std::fs::create_dir(&cwd).unwrap();
+        std::fs::write(cwd.join("train.py"), "").unwrap();
+
+        let (abspath, relpath) = program_paths("train.py", &cwd, root.path()).unwrap();
+        assert_eq!(
+            abspath,
+            cwd.join("train.py")
+                .canonicalize()
+                .unwrap()
+                .display()
+                .to_string()
+        );
+        assert_eq!(
+            relpath,
+            Path::new("scripts").join("train.py").display().to_string()
+        );
+
+        assert_eq!(program_paths("missing.py", &cwd, root.path()), None);
+        assert_eq!(program_paths("train.py", &cwd, &cwd.join("nested")), None);
+    }
+
+    #[test]
+    fn stride_advances_auto_steps() {
+        let mut steps = Steps::default();
+        for expected in [0, 10, 20] {
+            assert_eq!(log(&mut steps, None, None, 10), expected);
+        }
+        assert_eq!(steps.committed, Some(20));
+    }
+
+    #[test]
+    fn explicit_step_rebases_stride() {
+        // auto steps continue from the explicit step, not from the old stride grid
+        let mut steps = Steps::default();
+        assert_eq!(log(&mut steps, None, None, 10), 0);
+        assert_eq!(log(&mut steps, Some(5), Some(true), 10), 5);
+        assert_eq!(log(&mut steps, None, None, 10), 15);
+        assert_eq!(steps.committed, Some(15));
+    }
+
+    // a run whose wandb-core is the returned socket
+    fn fake_run(core: &TcpListener, project: &str) -> (Run, TcpStream) {
+        let stream = TcpStream::connect(core.local_addr().unwrap()).unwrap();
+        let (core_stream, _) = core.accept().unwrap();
+        let mut settings = Settings::default();
+        settings.proto.project = Some(project.to_string());
+        settings.proto.run_id = Some(generate_id(8));
+        let run = Run {
+            settings,
+            interface: Interface::new(Connection::new(stream)),
+            summary: Summary::default(),
+            steps: Steps::default(),
+            git: None,
+            finished: false,
+        };
+        (run, core_stream)
+    }
+
+    // step, flush flag and keys of the next history row wandb-core receives
+    fn next_row(core_stream: &mut TcpStream) -> (i64, bool, Vec<String>) {
+        let mut header = [0; 5];
+        core_stream.read_exact(&mut header).unwrap();
+        assert_eq!(header[0], b'W');
+        let mut body = vec![0; u32::from_le_bytes(header[1..].try_into().unwrap()) as usize];
+        core_stream.read_exact(&mut body).unwrap();
+
+        let request = wandb_internal::ServerRequest::decode(body.as_slice()).unwrap();
+        let Some(wandb_internal::server_request::ServerRequestType::RecordPublish(record)) =
+            request.server_request_type
+        else {
+            panic!("expected a published record");
+        };
+        let Some(wandb_internal::record::RecordType::Request(wandb_internal::Request {
+            request_type: Some(wandb_internal::request::RequestType::PartialHistory(history)),
+        })) = record.record_type
+        else {
+            panic!("expected a history row");
+        };
+        let mut keys: Vec<String> = history.item.into_iter().map(|item| item.key).collect();
+        keys.sort();
+        (
+            history.step.unwrap().num,
+            history.action.unwrap().flush,
+            keys,
+        )
+    }
+
+    fn row(values: &[(&str, f64)]) -> HashMap<String, Value<'static>> {
+        values
+            .iter()
+            .map(|(key, value)| (key.to_string(), Value::Float(*value)))
+            .collect()
+    }
+
+    #[test]
+    fn group_log_lands_in_every_project() {
+        let core = TcpListener::bind("127.0.0.1:0").unwrap();
+        let (team, mut team_core) = fake_run(&core, "team-project");
+        let (personal, mut personal_core) = fake_run(&core, "my-project");
+        let mut group = RunGroup::new(vec![team, personal]);
+
+        group.log(row(&[("loss", 0.5)]), None, None, None).unwrap();
+        let expected = (0, true, vec!["loss".to_string()]);
+        assert_eq!(next_row(&mut team_core), expected);
+        assert_eq!(next_row(&mut personal_core), expected);
+
+        // a row for the team project only, the personal run skips step 1
+        let team_only = Some(vec!["team-project".to_string()]);
+        group
+            .log(row(&[("acc", 0.9)]), None, None, team_only)
+            .unwrap();
+        assert_eq!(next_row(&mut team_core), (1, true, vec!["acc".to_string()]));
+
+        group.log(row(&[("loss", 0.4)]), None, None, None).unwrap();
+        let expected = (2, true, vec!["loss".to_string()]);
+        assert_eq!(next_row(&mut team_core), expected);
+        assert_eq!(next_row(&mut personal_core), expected);
+        assert_eq!(group.runs[1].step(), Some(2));
+
+        let unknown = Some(vec!["other-project".to_string()]);
+        assert!(group
+            .log(row(&[("loss", 0.3)]), None, None, unknown)
+            .is_err());
+    }
+}
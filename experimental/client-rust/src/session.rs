@@ -1,3 +1,4 @@
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 
 use core::panic;
@@ -11,7 +12,8 @@ use tracing;
 
 use crate::connection::{Connection, Interface};
 use crate::launcher::Launcher;
-use crate::run::Run;
+use crate::metric::Summary;
+use crate::run::{Run, RunGroup, Steps};
 use crate::settings::Settings;
 
 #[pyclass]
@@ -20,7 +22,7 @@ pub struct Session {
     addr: String,
 }
 
-pub fn get_core_address() -> String {
+pub fn get_core_address(ca_bundle: Option<String>) -> String {
     // TODO: get and set WANDB_CORE env variable to handle multiprocessing
     let current_dir =
         env::var("_WANDB_CORE_PATH").expect("Environment variable _WANDB_CORE_PATH is not set");
@@ -30,35 +32,69 @@ pub fn get_core_address() -> String {
         .into_os_string()
         .into_string()
         .expect("Failed to convert path to string");
-    let launcher = Launcher { command: core_cmd };
+    if let Some(ca_bundle) = &ca_bundle {
+        tracing::info!("Using CA bundle {}", ca_bundle);
+    }
+    let launcher = Launcher {
+        command: core_cmd,
+        ca_bundle,
+    };
     let port = launcher.start();
     format!("127.0.0.1:{}", port)
 }
 
+fn settings_for_project(settings: &Settings, project: Option<String>) -> Settings {
+    let mut settings = settings.clone();
+    if project.is_some() {
+        settings.proto.project = project;
+    }
+    settings
+}
+
 #[pymethods]
 impl Session {
     #[new]
     pub fn new(settings: Settings) -> Session {
-        let addr = get_core_address();
+        let addr = get_core_address(settings.ca_bundle.clone());
         let session = Session { settings, addr };
         tracing::debug!("Session created");
 
         session
     }
 
-    pub fn init_run(&self, run_id: Option<String>) -> Run {
+    /// Starts a run, optionally in a project other than the session's
+    #[pyo3(signature = (run_id=None, project=None))]
+    pub fn init_run(&self, run_id: Option<String>, project: Option<String>) -> Run {
         let conn = Connection::new(self.connect());
         let interface = Interface::new(conn);
 
         let mut run = Run {
-            settings: self.settings.clone(),
+            settings: settings_for_project(&self.settings, project),
             interface,
+            summary: Summary::default(),
+            steps: Steps::default(),
+            git: None,
+            finished: false,
         };
 
         run.init(run_id);
 
         return run;
     }
+
+    /// Starts a run in each project, all logged to at once through the group
+    pub fn init_group(&self, projects: Vec<String>) -> PyResult<RunGroup> {
+        if projects.is_empty() {
+            return Err(PyValueError::new_err(
+                "A run group needs at least one project",
+            ));
+        }
+        let runs = projects
+            .into_iter()
+            .map(|project| self.init_run(None, Some(project)))
+            .collect();
+        Ok(RunGroup::new(runs))
+    }
 }
 
 impl Session {
@@ -80,3 +116,20 @@ impl Session {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_can_target_different_projects() {
+        let settings = Settings::default();
+        let team = settings_for_project(&settings, Some("team-project".to_string()));
+        let personal = settings_for_project(&settings, Some("my-project".to_string()));
+        let default = settings_for_project(&settings, None);
+
+        assert_eq!(team.proto.project.as_deref(), Some("team-project"));
+        assert_eq!(personal.proto.project.as_deref(), Some("my-project"));
+        assert_eq!(default.proto.project, settings.proto.project);
+    }
+}
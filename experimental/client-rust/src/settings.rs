@@ -1,16 +1,84 @@
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
+use pyo3::types::PyDict;
 
 use crate::wandb_internal::Settings as SettingsProto;
 
+// must list exactly the keyword arguments of `Settings::new`,
+// `every_settings_key_is_accepted` checks this
+const SETTINGS_KEYS: [&str; 15] = [
+    "base_url",
+    "ca_bundle",
+    "disable_git",
+    "log_internal",
+    "mode",
+    "program",
+    "project",
+    "reinit",
+    "save_code",
+    "stats_pid",
+    "stats_sample_rate_seconds",
+    "stats_samples_to_average",
+    "step_stride",
+    "sync_file",
+    "sync_dir",
+];
+
+// below this, a suggestion is more likely to confuse than help
+const SUGGESTION_THRESHOLD: f64 = 0.8;
+
+/// Find the known key closest to an unrecognized one, if any is close enough
+pub fn suggest_key<'a>(key: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    candidates
+        .iter()
+        .map(|candidate| (*candidate, strsim::jaro_winkler(key, candidate)))
+        .filter(|(_, similarity)| *similarity >= SUGGESTION_THRESHOLD)
+        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+        .map(|(candidate, _)| candidate)
+}
+
+fn unknown_key_error(key: &str) -> PyErr {
+    match suggest_key(key, &SETTINGS_KEYS) {
+        Some(suggestion) => PyValueError::new_err(format!(
+            "Unknown setting '{}', did you mean `{}`?",
+            key, suggestion
+        )),
+        None => PyValueError::new_err(format!("Unknown setting '{}'", key)),
+    }
+}
+
 #[pyclass]
 #[derive(Clone, Debug)]
 pub struct Settings {
     pub proto: SettingsProto,
+    // not part of the proto, only needed to launch wandb-core
+    pub ca_bundle: Option<String>,
+    // how far the step advances on each commit without an explicit step
+    pub step_stride: i64,
 }
 
 #[pymethods]
 impl Settings {
     #[new]
+    #[pyo3(signature = (
+        base_url=None,
+        log_internal=None,
+        mode=None,
+        project=None,
+        stats_pid=None,
+        stats_sample_rate_seconds=None,
+        stats_samples_to_average=None,
+        sync_file=None,
+        sync_dir=None,
+        *,
+        ca_bundle=None,
+        disable_git=None,
+        program=None,
+        reinit=None,
+        save_code=None,
+        step_stride=None,
+        **kwargs
+    ))]
     pub fn new(
         base_url: Option<String>,
         log_internal: Option<String>,
@@ -21,13 +89,38 @@ impl Settings {
         stats_samples_to_average: Option<i32>,
         sync_file: Option<String>,
         sync_dir: Option<String>,
-    ) -> Settings {
+        ca_bundle: Option<String>,
+        disable_git: Option<bool>,
+        program: Option<String>,
+        reinit: Option<bool>,
+        save_code: Option<bool>,
+        step_stride: Option<i64>,
+        kwargs: Option<&PyDict>,
+    ) -> PyResult<Settings> {
+        if let Some(kwargs) = kwargs {
+            if let Some(key) = kwargs.keys().iter().next() {
+                return Err(unknown_key_error(&key.to_string()));
+            }
+        }
+
+        let step_stride = step_stride.unwrap_or(1);
+        if step_stride < 1 {
+            return Err(PyValueError::new_err(format!(
+                "step_stride must be at least 1, got {}",
+                step_stride
+            )));
+        }
+
         let mut proto = Settings::default().proto.clone();
 
         proto.base_url = base_url.or(proto.base_url);
+        proto.disable_git = disable_git.or(proto.disable_git);
         proto.log_internal = log_internal.or(proto.log_internal);
         proto.mode = mode.or(proto.mode);
+        proto.program = program.or(proto.program);
         proto.project = project.or(proto.project);
+        proto.reinit = reinit.or(proto.reinit);
+        proto.save_code = save_code.or(proto.save_code);
         proto.stats_pid = stats_pid.or(proto.stats_pid);
         proto.stats_sample_rate_seconds =
             stats_sample_rate_seconds.or(proto.stats_sample_rate_seconds);
@@ -36,7 +129,14 @@ impl Settings {
         proto.sync_file = sync_file.or(proto.sync_file);
         proto.sync_dir = sync_dir.or(proto.sync_dir);
 
-        Settings { proto }
+        let settings = Settings {
+            proto,
+            ca_bundle: ca_bundle.or(Settings::default().ca_bundle),
+            step_stride,
+        };
+        settings.validate()?;
+
+        Ok(settings)
     }
 
     // TODO: auto-generate all getters and setters? tried a bunch of stuff, but no luck so far
@@ -77,9 +177,34 @@ impl Settings {
 }
 
 impl Settings {
+    /// Checks settings that `Default` can't reject, such as `WANDB_CA_BUNDLE`
+    pub fn validate(&self) -> PyResult<()> {
+        if let Some(path) = &self.ca_bundle {
+            // Go verifies certificates with the system keychain on macOS and
+            // ignores SSL_CERT_FILE there, refuse rather than drop the bundle
+            if cfg!(target_os = "macos") {
+                return Err(PyValueError::new_err(
+                    "ca_bundle is not supported on macOS, add the CA to the system keychain instead",
+                ));
+            }
+            if !std::path::Path::new(path).is_file() {
+                return Err(PyValueError::new_err(format!(
+                    "CA bundle '{}' does not exist",
+                    path
+                )));
+            }
+        }
+        Ok(())
+    }
+
     pub fn clone(&self) -> Settings {
         let proto = self.proto.clone();
-        Settings { proto }
+        let ca_bundle = self.ca_bundle.clone();
+        Settings {
+            proto,
+            ca_bundle,
+            step_stride: self.step_stride,
+        }
     }
 }
 
@@ -88,10 +213,14 @@ impl Default for Settings {
         Settings {
             proto: SettingsProto {
                 base_url: Some("https://api.wandb.ai".to_string()),
+                disable_git: Some(false),
                 log_internal: Some("wandb-internal.log".to_string()),
                 mode: Some("online".to_string()),
                 offline: Some(false),
+                program: std::env::var("WANDB_PROGRAM").ok(),
                 project: Some("uncategorized".to_string()),
+                reinit: Some(false),
+                save_code: Some(false),
                 stats_pid: Some(std::process::id() as i32),
                 stats_sample_rate_seconds: Some(5.0),
                 stats_samples_to_average: Some(1),
@@ -102,6 +231,54 @@ impl Default for Settings {
                 files_dir: Some("undefined".to_string()),
                 ..Default::default()
             },
+            ca_bundle: std::env::var("WANDB_CA_BUNDLE").ok(),
+            step_stride: 1,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suggests_closest_key() {
+        assert_eq!(suggest_key("proejct", &SETTINGS_KEYS), Some("project"));
+        assert_eq!(suggest_key("sync_dri", &SETTINGS_KEYS), Some("sync_dir"));
+    }
+
+    #[test]
+    fn no_suggestion_for_unrelated_key() {
+        assert_eq!(suggest_key("batch_size", &SETTINGS_KEYS), None);
+    }
+
+    #[test]
+    fn every_settings_key_is_accepted() {
+        pyo3::prepare_freethreaded_python();
+        let ca_bundle = tempfile::NamedTempFile::new().unwrap();
+        Python::with_gil(|py| {
+            for key in SETTINGS_KEYS {
+                let value = match key {
+                    "ca_bundle" if cfg!(target_os = "macos") => continue,
+                    "ca_bundle" => ca_bundle.path().to_str().unwrap().into_py(py),
+                    "disable_git" | "reinit" | "save_code" => true.into_py(py),
+                    "stats_pid" | "stats_samples_to_average" | "step_stride" => 1.into_py(py),
+                    "stats_sample_rate_seconds" => 1.0.into_py(py),
+                    _ => "value".into_py(py),
+                };
+                let kwargs = PyDict::new(py);
+                kwargs.set_item(key, value).unwrap();
+                let result = py.get_type::<Settings>().call((), Some(kwargs));
+                assert!(result.is_ok(), "{} was rejected: {:?}", key, result);
+            }
+
+            let kwargs = PyDict::new(py);
+            kwargs.set_item("proejct", "value").unwrap();
+            let error = py
+                .get_type::<Settings>()
+                .call((), Some(kwargs))
+                .unwrap_err();
+            assert!(error.to_string().contains("did you mean `project`?"));
+        });
+    }
+}